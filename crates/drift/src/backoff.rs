@@ -0,0 +1,93 @@
+use std::time::Duration;
+
+/// Jitter strategy applied on top of the computed backoff delay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Jitter {
+    /// Sleep for exactly the computed delay.
+    None,
+    /// Sleep for a uniformly random duration in `[0, delay]`.
+    Full,
+}
+
+/// An exponential backoff policy for retrying a failed job.
+///
+/// `delay(attempt) = min(base * factor^attempt, max_delay)`, where `attempt`
+/// is the number of consecutive failures observed so far (reset to `0` as
+/// soon as a run succeeds).
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+    pub base: Duration,
+    pub factor: f64,
+    pub max_delay: Duration,
+    pub jitter: Jitter,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, factor: f64, max_delay: Duration) -> Self {
+        Self {
+            base,
+            factor,
+            max_delay,
+            jitter: Jitter::None,
+        }
+    }
+
+    /// Enable "full jitter": the actual sleep is a uniform random value in
+    /// `[0, delay(attempt)]` instead of the delay itself.
+    pub fn with_full_jitter(mut self) -> Self {
+        self.jitter = Jitter::Full;
+        self
+    }
+
+    /// The backoff delay for the given number of consecutive failures,
+    /// before jitter is applied.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let scaled = self.base.as_secs_f64() * self.factor.powi(attempt as i32);
+
+        Duration::from_secs_f64(scaled.min(self.max_delay.as_secs_f64()).max(0.0))
+    }
+
+    /// The duration the caller should actually sleep for, with jitter
+    /// applied if configured.
+    pub fn sleep_duration(&self, attempt: u32) -> Duration {
+        let delay = self.delay(attempt);
+
+        match self.jitter {
+            Jitter::None => delay,
+            Jitter::Full => {
+                let millis = delay.as_millis() as u64;
+                if millis == 0 {
+                    Duration::default()
+                } else {
+                    Duration::from_millis(fastrand::u64(0..=millis))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_grows_exponentially_and_caps() {
+        let backoff = Backoff::new(Duration::from_secs(1), 2.0, Duration::from_secs(10));
+
+        assert_eq!(backoff.delay(0), Duration::from_secs(1));
+        assert_eq!(backoff.delay(1), Duration::from_secs(2));
+        assert_eq!(backoff.delay(2), Duration::from_secs(4));
+        assert_eq!(backoff.delay(10), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_full_jitter_stays_within_bounds() {
+        let backoff =
+            Backoff::new(Duration::from_secs(1), 2.0, Duration::from_secs(10)).with_full_jitter();
+
+        for attempt in 0..5 {
+            let sleep = backoff.sleep_duration(attempt);
+            assert!(sleep <= backoff.delay(attempt));
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::{future::Future, sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use tokio_util::sync::CancellationToken;
+
+use crate::{DriftError, Drifter};
+
+/// Everything a job might want to know about the run it's currently in,
+/// passed to [`Drifter::execute`].
+#[derive(Debug, Clone)]
+pub struct RunContext {
+    /// Cancellation token for this run; cancelling it signals the job to
+    /// stop cooperatively.
+    pub token: CancellationToken,
+    /// The time this run was scheduled to fire.
+    pub scheduled_at: DateTime<Utc>,
+    /// The time this run actually started.
+    pub fired_at: DateTime<Utc>,
+    /// The number of consecutive failures immediately preceding this run
+    /// (`0` if the previous run succeeded, or this is the first run).
+    pub attempt: u32,
+    /// The time of the last successful run, if any.
+    pub last_success: Option<DateTime<Utc>>,
+    /// A monotonically increasing sequence number, incremented once per
+    /// run dispatched by the scheduling loop (starting at `1`).
+    pub sequence: u64,
+}
+
+pub fn schedule_with_context<F, Fut>(interval: Duration, func: F) -> CancellationToken
+where
+    F: Fn(RunContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    let drifter = FuncContextDrifter::new(func);
+
+    crate::schedule_drifter(interval, drifter)
+}
+
+pub fn schedule_cron_with_context<F, Fut>(cron: &str, func: F) -> anyhow::Result<CancellationToken>
+where
+    F: Fn(RunContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    let drifter = FuncContextDrifter::new(func);
+
+    crate::schedule_drifter_cron(cron, drifter)
+}
+
+pub(crate) struct FuncContextDrifter<F, Fut>
+where
+    F: Fn(RunContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    func: Arc<F>,
+}
+
+impl<F, Fut> Clone for FuncContextDrifter<F, Fut>
+where
+    F: Fn(RunContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            func: self.func.clone(),
+        }
+    }
+}
+
+impl<F, Fut> FuncContextDrifter<F, Fut>
+where
+    F: Fn(RunContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    pub(crate) fn new(func: F) -> Self {
+        Self {
+            func: Arc::new(func),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<F, Fut> Drifter for FuncContextDrifter<F, Fut>
+where
+    F: Fn(RunContext) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<(), DriftError>> + Send,
+{
+    async fn execute(&self, ctx: RunContext) -> anyhow::Result<()> {
+        if let Err(e) = (self.func)(ctx).await {
+            anyhow::bail!(e)
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_schedule_with_context_sees_attempt_and_sequence() -> anyhow::Result<()> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let token = schedule_with_context(Duration::from_millis(50), {
+            let seen = seen.clone();
+            move |ctx: RunContext| {
+                let seen = seen.clone();
+                async move {
+                    seen.lock().unwrap().push((ctx.sequence, ctx.attempt));
+                    Ok(())
+                }
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert!(!token.is_cancelled());
+
+        let seen = seen.lock().unwrap();
+        assert!(seen.len() >= 2);
+        assert_eq!(seen[0], (1, 0));
+        assert_eq!(seen[1], (2, 0));
+
+        Ok(())
+    }
+}
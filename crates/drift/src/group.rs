@@ -0,0 +1,125 @@
+use std::{str::FromStr, time::Duration};
+
+use tokio::time;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::{run_cron_loop, run_interval_loop, Drifter, OverlapPolicy};
+
+/// A group of drift jobs that can be shut down gracefully.
+///
+/// Cancelling a lone [`CancellationToken`] only stops the scheduling loop;
+/// it gives no way to wait for the currently-executing `drifter.execute`
+/// future to actually finish. `DriftGroup` spawns every job through a
+/// [`TaskTracker`] so [`shutdown`](DriftGroup::shutdown) can cancel the root
+/// token and then wait for all in-flight executions to drain, which is what
+/// you want on SIGTERM for jobs that write to databases or flush buffers.
+#[derive(Default)]
+pub struct DriftGroup {
+    tracker: TaskTracker,
+    token: CancellationToken,
+}
+
+impl DriftGroup {
+    pub fn new() -> Self {
+        Self {
+            tracker: TaskTracker::new(),
+            token: CancellationToken::new(),
+        }
+    }
+
+    /// The root cancellation token for this group. Jobs spawned via
+    /// [`spawn`](DriftGroup::spawn)/[`spawn_cron`](DriftGroup::spawn_cron)
+    /// derive their cancellation from a child of this token.
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// Spawn `drifter` to run every `interval`, tracked by this group.
+    pub fn spawn<FDrifter>(&self, interval: Duration, drifter: FDrifter)
+    where
+        FDrifter: Drifter + Send + 'static + Clone,
+    {
+        let token = self.token.child_token();
+        self.tracker
+            .spawn(run_interval_loop(token, interval, drifter));
+    }
+
+    /// Spawn `drifter` to run on the given cron expression, tracked by this
+    /// group.
+    pub fn spawn_cron<FDrifter>(&self, cron: &str, drifter: FDrifter) -> anyhow::Result<()>
+    where
+        FDrifter: Drifter + Send + 'static + Clone,
+    {
+        let schedule = ::cron::Schedule::from_str(cron)?;
+        let token = self.token.child_token();
+        self.tracker
+            .spawn(run_cron_loop(token, schedule, drifter, OverlapPolicy::Skip));
+
+        Ok(())
+    }
+
+    /// Cancel the root token, then wait for every spawned job to finish its
+    /// current execution. If `timeout` elapses before all jobs have
+    /// drained, returns `false` without waiting any longer; otherwise
+    /// returns `true`.
+    pub async fn shutdown(&self, timeout: Option<Duration>) -> bool {
+        self.token.cancel();
+        self.tracker.close();
+
+        match timeout {
+            Some(timeout) => time::timeout(timeout, self.tracker.wait()).await.is_ok(),
+            None => {
+                self.tracker.wait().await;
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use crate::RunContext;
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    struct SlowDrifter {
+        running: Arc<Mutex<bool>>,
+        completed: Arc<Mutex<usize>>,
+    }
+
+    #[async_trait]
+    impl Drifter for SlowDrifter {
+        async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
+            *self.running.lock().unwrap() = true;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            *self.completed.lock().unwrap() += 1;
+            *self.running.lock().unwrap() = false;
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drift_group_shutdown_drains_in_flight_job() -> anyhow::Result<()> {
+        let drifter = SlowDrifter::default();
+
+        let group = DriftGroup::new();
+        group.spawn(Duration::from_millis(50), drifter.clone());
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(*drifter.running.lock().unwrap());
+
+        let drained = group.shutdown(Some(Duration::from_secs(1))).await;
+
+        assert!(drained);
+        assert!(!*drifter.running.lock().unwrap());
+        assert_eq!(*drifter.completed.lock().unwrap(), 1);
+
+        Ok(())
+    }
+}
@@ -6,6 +6,26 @@ use std::future::Future;
 use tokio::time;
 use tokio_util::sync::CancellationToken;
 
+mod backoff;
+mod context;
+mod group;
+mod local;
+mod overlap;
+mod scheduler;
+mod throttle;
+
+pub(crate) use overlap::{run_cron_loop, run_cron_loop_with_retry};
+
+pub use backoff::{Backoff, Jitter};
+pub use context::{schedule_cron_with_context, schedule_with_context, RunContext};
+pub use group::DriftGroup;
+pub use local::{
+    schedule_drifter_cron_local, schedule_drifter_local, schedule_local, LocalDrifter,
+};
+pub use overlap::OverlapPolicy;
+pub use scheduler::Scheduler;
+pub use throttle::{schedule_drifter_throttled, schedule_throttled, Throttle};
+
 #[derive(Debug, thiserror::Error)]
 pub enum DriftError {
     #[error("job failed with inner error: {0}")]
@@ -32,10 +52,39 @@ where
     schedule_drifter_cron(cron, drifter)
 }
 
+pub fn schedule_cron_with_overlap<F, Fut>(
+    cron: &str,
+    func: F,
+    policy: OverlapPolicy,
+) -> anyhow::Result<CancellationToken>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    let drifter = FuncDrifter::new(func);
+
+    schedule_drifter_cron_with_overlap(cron, drifter, policy)
+}
+
 pub fn schedule_drifter_cron<FDrifter>(
     cron: &str,
     drifter: FDrifter,
 ) -> anyhow::Result<CancellationToken>
+where
+    FDrifter: Drifter + Send + 'static,
+    FDrifter: Clone,
+{
+    schedule_drifter_cron_with_overlap(cron, drifter, OverlapPolicy::Skip)
+}
+
+/// Like [`schedule_drifter_cron`], but with explicit control over what
+/// happens when a tick arrives while the previous execution is still
+/// running. See [`OverlapPolicy`].
+pub fn schedule_drifter_cron_with_overlap<FDrifter>(
+    cron: &str,
+    drifter: FDrifter,
+    policy: OverlapPolicy,
+) -> anyhow::Result<CancellationToken>
 where
     FDrifter: Drifter + Send + 'static,
     FDrifter: Clone,
@@ -44,118 +93,196 @@ where
 
     let cancellation_token = CancellationToken::new();
 
-    tokio::spawn({
-        let cancellation_token = cancellation_token.clone();
-        let drifter = drifter.clone();
-
-        async move {
-            let upcoming = schedule.upcoming(Utc {});
-
-            let child_token = cancellation_token.child_token();
-            for datetime in upcoming {
-                let now = Utc::now();
-
-                let diff = datetime - now;
-                if diff <= TimeDelta::zero() {
-                    tracing::info!(
-                        "job schedule for {} was in the past: {}, skipping iteration",
-                        datetime.to_string(),
-                        now.to_string()
-                    );
-                    continue;
-                }
+    tokio::spawn(run_cron_loop(
+        cancellation_token.clone(),
+        schedule,
+        drifter,
+        policy,
+    ));
 
-                let diff = diff.to_std().expect("to be able to get diff time");
-                let sleep = time::sleep(diff);
-                tokio::pin!(sleep);
+    Ok(cancellation_token)
+}
 
-                tracing::debug!(
-                    "schedule job: {}, waiting: {}s for execution",
-                    datetime.to_string(),
-                    diff.as_secs()
-                );
+pub fn schedule_drifter<FDrifter>(interval: Duration, drifter: FDrifter) -> CancellationToken
+where
+    FDrifter: Drifter + Send + 'static,
+    FDrifter: Clone,
+{
+    let cancellation_token = CancellationToken::new();
 
-                tokio::select! {
-                    _ = cancellation_token.cancelled() => {
-                        tracing::trace!("stopping drift job");
+    tokio::spawn(run_interval_loop(
+        cancellation_token.clone(),
+        interval,
+        drifter,
+    ));
 
-                        break
-                    }
-                    _ = &mut sleep => {
-                        let start = std::time::Instant::now();
+    cancellation_token
+}
+
+/// The interval scheduling loop, shared between [`schedule_drifter`] and
+/// [`Scheduler::add`](crate::Scheduler::add), which spawns it directly onto
+/// a `JoinMap` instead of a detached task. Never retries a failed run other
+/// than waiting out the rest of the current `interval`.
+pub(crate) async fn run_interval_loop<FDrifter>(
+    cancellation_token: CancellationToken,
+    interval: Duration,
+    drifter: FDrifter,
+) where
+    FDrifter: Drifter,
+{
+    run_interval_loop_with_retry(cancellation_token, interval, drifter, None).await
+}
 
-                        tracing::debug!("running job");
-                        if let Err(e) = drifter.execute(child_token.child_token()).await {
-                            tracing::error!("drift job failed with error: {}", e);
-                            continue
-                        }
+/// Like [`run_interval_loop`], but on failure `retry` (if set) overrides the
+/// wait before the next attempt with `backoff.sleep_duration(attempt)`
+/// instead of waiting for the rest of `interval`. The attempt counter lives
+/// inside this loop and resets to `0` as soon as a run succeeds.
+pub(crate) async fn run_interval_loop_with_retry<FDrifter>(
+    cancellation_token: CancellationToken,
+    interval: Duration,
+    drifter: FDrifter,
+    retry: Option<Backoff>,
+) where
+    FDrifter: Drifter,
+{
+    let mut wait = Duration::default();
+    let mut attempt = 0u32;
+    let mut last_success = None;
+    let mut sequence = 0u64;
 
-                        let elapsed = start.elapsed();
+    loop {
+        let child_token = cancellation_token.child_token();
+        let sleep = time::sleep(wait);
+        tokio::pin!(sleep);
 
-                        tracing::debug!("job took: {}ms ", elapsed.as_millis());
-                    }
+        tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                tracing::trace!("stopping drift job");
 
+                break
+            }
+            _ = &mut sleep => {
+                let start = std::time::Instant::now();
+                let scheduled_at = Utc::now();
+                sequence += 1;
+
+                let ctx = RunContext {
+                    token: child_token,
+                    scheduled_at,
+                    fired_at: scheduled_at,
+                    attempt,
+                    last_success,
+                    sequence,
+                };
+
+                tracing::debug!("running job");
+                if let Err(e) = drifter.execute(ctx).await {
+                    wait = match &retry {
+                        Some(backoff) => backoff.sleep_duration(attempt),
+                        None => interval.saturating_sub(start.elapsed()),
+                    };
+                    attempt += 1;
+                    tracing::error!("drift job failed with error: {}, attempt: {}, waiting: {}s before trying again", e, attempt, wait.as_secs());
+                    continue
                 }
+
+                attempt = 0;
+                last_success = Some(Utc::now());
+                let elapsed = start.elapsed();
+                wait = interval.saturating_sub(elapsed);
+
+                let now: DateTime<Local> = Local::now();
+                let next: Option<DateTime<Local>> = now.checked_add_signed(TimeDelta::from_std(wait).expect("to be able to convert duration into time delta"));
+
+                tracing::debug!(now=now.to_string(), next=next.map(|n| n.to_string()), "job took: {}ms, waiting: {}ms for next run", elapsed.as_millis(), wait.as_millis() );
             }
-        }
-    });
 
-    Ok(cancellation_token)
+        }
+    }
 }
-pub fn schedule_drifter<FDrifter>(interval: Duration, drifter: FDrifter) -> CancellationToken
+
+pub fn schedule_with_backoff<F, Fut>(
+    interval: Duration,
+    backoff: Backoff,
+    func: F,
+) -> CancellationToken
 where
-    FDrifter: Drifter + Send + 'static,
-    FDrifter: Clone,
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
 {
-    let cancellation_token = CancellationToken::new();
-
-    tokio::spawn({
-        let cancellation_token = cancellation_token.clone();
-        let drifter = drifter.clone();
+    let drifter = FuncDrifter::new(func);
 
-        async move {
-            let mut wait = Duration::default();
+    schedule_drifter_with_backoff(interval, backoff, drifter)
+}
 
-            loop {
-                let child_token = cancellation_token.child_token();
-                let sleep = time::sleep(wait);
-                tokio::pin!(sleep);
+pub fn schedule_cron_with_backoff<F, Fut>(
+    cron: &str,
+    backoff: Backoff,
+    func: F,
+) -> anyhow::Result<CancellationToken>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    let drifter = FuncDrifter::new(func);
 
-                tokio::select! {
-                    _ = cancellation_token.cancelled() => {
-                        tracing::trace!("stopping drift job");
+    schedule_drifter_cron_with_backoff(cron, backoff, drifter)
+}
 
-                        break
-                    }
-                    _ = &mut sleep => {
-                        let start = std::time::Instant::now();
+/// Like [`schedule_drifter`], but on consecutive failures the wait before the
+/// next attempt follows `backoff` instead of the fixed `interval`. The
+/// attempt counter lives inside the spawned task and resets to `0` as soon
+/// as a run succeeds.
+pub fn schedule_drifter_with_backoff<FDrifter>(
+    interval: Duration,
+    backoff: Backoff,
+    drifter: FDrifter,
+) -> CancellationToken
+where
+    FDrifter: Drifter + Send + 'static,
+    FDrifter: Clone,
+{
+    let cancellation_token = CancellationToken::new();
 
-                        tracing::debug!("running job");
-                        if let Err(e) = drifter.execute(child_token).await {
-                            let elapsed = start.elapsed();
-                            wait = interval.saturating_sub(elapsed);
-                            tracing::error!("drift job failed with error: {}, waiting: {}s before trying again", e, wait.as_secs());
-                            continue
-                        }
+    tokio::spawn(run_interval_loop_with_retry(
+        cancellation_token.clone(),
+        interval,
+        drifter,
+        Some(backoff),
+    ));
 
-                        let elapsed = start.elapsed();
-                        wait = interval.saturating_sub(elapsed);
+    cancellation_token
+}
 
-                        let now: DateTime<Local> = Local::now();
-                        let next: Option<DateTime<Local>> = now.checked_add_signed(TimeDelta::from_std(wait).expect("to be able to convert duration into time delta"));
+/// Like [`schedule_drifter_cron`], but on failure the job is retried after
+/// `backoff.sleep_duration(attempt)` instead of waiting for the next natural
+/// cron tick. The attempt counter resets to `0` as soon as a run succeeds,
+/// at which point the loop resumes following the cron schedule.
+pub fn schedule_drifter_cron_with_backoff<FDrifter>(
+    cron: &str,
+    backoff: Backoff,
+    drifter: FDrifter,
+) -> anyhow::Result<CancellationToken>
+where
+    FDrifter: Drifter + Send + 'static,
+    FDrifter: Clone,
+{
+    let schedule = ::cron::Schedule::from_str(cron)?;
 
-                        tracing::debug!(now=now.to_string(), next=next.map(|n| n.to_string()), "job took: {}ms, waiting: {}ms for next run", elapsed.as_millis(), wait.as_millis() );
-                    }
+    let cancellation_token = CancellationToken::new();
 
-                }
-            }
-        }
-    });
+    tokio::spawn(run_cron_loop_with_retry(
+        cancellation_token.clone(),
+        schedule,
+        drifter,
+        OverlapPolicy::Skip,
+        Some(backoff),
+    ));
 
-    cancellation_token
+    Ok(cancellation_token)
 }
 
-struct FuncDrifter<F, Fut>
+pub(crate) struct FuncDrifter<F, Fut>
 where
     F: Fn() -> Fut + Send + Sync + 'static,
     Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
@@ -180,7 +307,7 @@ where
     F: Fn() -> Fut + Send + Sync + 'static,
     Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
 {
-    fn new(func: F) -> Self {
+    pub(crate) fn new(func: F) -> Self {
         Self {
             func: Arc::new(func),
         }
@@ -201,7 +328,7 @@ where
     F: Fn() -> Fut + Send + Sync,
     Fut: Future<Output = Result<(), DriftError>> + Send,
 {
-    async fn execute(&self, token: CancellationToken) -> anyhow::Result<()> {
+    async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
         self.execute_func().await?;
 
         Ok(())
@@ -210,7 +337,7 @@ where
 
 #[async_trait]
 pub trait Drifter {
-    async fn execute(&self, token: CancellationToken) -> anyhow::Result<()>;
+    async fn execute(&self, ctx: RunContext) -> anyhow::Result<()>;
 }
 
 #[cfg(test)]
@@ -239,7 +366,7 @@ mod tests {
 
     #[async_trait]
     impl Drifter for CounterDrifter {
-        async fn execute(&self, _cancellation_token: CancellationToken) -> anyhow::Result<()> {
+        async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
             let mut counter = self.counter.lock().unwrap();
             *counter += 1;
 
@@ -278,6 +405,47 @@ mod tests {
         Ok(())
     }
 
+    #[derive(Clone)]
+    pub struct FlakyDrifter {
+        attempts: Arc<Mutex<usize>>,
+        fail_until: usize,
+    }
+
+    #[async_trait]
+    impl Drifter for FlakyDrifter {
+        async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
+            let mut attempts = self.attempts.lock().unwrap();
+            *attempts += 1;
+
+            if *attempts <= self.fail_until {
+                anyhow::bail!("still flaky");
+            }
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backoff_retries_until_success() -> anyhow::Result<()> {
+        let drifter = FlakyDrifter {
+            attempts: Arc::new(Mutex::new(0)),
+            fail_until: 2,
+        };
+
+        let backoff = Backoff::new(Duration::from_millis(10), 1.0, Duration::from_millis(10));
+
+        let token =
+            schedule_drifter_with_backoff(Duration::from_millis(500), backoff, drifter.clone());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        assert!(!token.is_cancelled());
+
+        let attempts = drifter.attempts.lock().unwrap();
+        assert!(*attempts >= 3);
+
+        Ok(())
+    }
+
     #[tokio::test]
     #[traced_test]
     async fn test_calls_trace_on_start_and_end() -> anyhow::Result<()> {
@@ -362,4 +530,21 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_can_schedule_throttled_job() -> anyhow::Result<()> {
+        let drifter = CounterDrifter::default();
+
+        let throttle = Throttle::new(0.5);
+        let token = schedule_drifter_throttled(throttle, drifter.clone());
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert!(!token.is_cancelled());
+
+        let counter = *drifter.counter.lock().unwrap();
+        assert!(counter >= 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,295 @@
+use std::{rc::Rc, str::FromStr, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use std::future::Future;
+use tokio::{task::LocalSet, time};
+use tokio_util::sync::CancellationToken;
+
+use crate::{DriftError, RunContext};
+
+/// Like [`Drifter`](crate::Drifter), but for jobs whose execution future is
+/// not [`Send`] (e.g. it captures an `Rc`, a non-Send FFI handle, or a
+/// single-threaded connection object). Scheduled via [`schedule_local`] /
+/// [`schedule_drifter_local`] onto a [`LocalSet`] instead of the
+/// multithreaded runtime.
+#[async_trait(?Send)]
+pub trait LocalDrifter {
+    async fn execute(&self, ctx: RunContext) -> anyhow::Result<()>;
+}
+
+/// Schedule `func` to run every `interval` on `local_set`.
+///
+/// `local_set` must be driven by the caller (e.g. via
+/// `local_set.run_until(...).await` or by being entered on a
+/// current-thread runtime) for the job to actually make progress.
+pub fn schedule_local<F, Fut>(
+    local_set: &LocalSet,
+    interval: Duration,
+    func: F,
+) -> CancellationToken
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + 'static,
+{
+    let drifter = FuncLocalDrifter::new(func);
+
+    schedule_drifter_local(local_set, interval, drifter)
+}
+
+/// Schedule `drifter` to run every `interval` on `local_set`. See
+/// [`schedule_local`] for the `!Send` jobs this enables.
+pub fn schedule_drifter_local<FDrifter>(
+    local_set: &LocalSet,
+    interval: Duration,
+    drifter: FDrifter,
+) -> CancellationToken
+where
+    FDrifter: LocalDrifter + Clone + 'static,
+{
+    let cancellation_token = CancellationToken::new();
+
+    local_set.spawn_local(run_local_interval_loop(
+        cancellation_token.clone(),
+        interval,
+        drifter,
+    ));
+
+    cancellation_token
+}
+
+/// Schedule `drifter` to run on the given cron expression on `local_set`.
+pub fn schedule_drifter_cron_local<FDrifter>(
+    local_set: &LocalSet,
+    cron: &str,
+    drifter: FDrifter,
+) -> anyhow::Result<CancellationToken>
+where
+    FDrifter: LocalDrifter + Clone + 'static,
+{
+    let schedule = ::cron::Schedule::from_str(cron)?;
+    let cancellation_token = CancellationToken::new();
+
+    local_set.spawn_local(run_local_cron_loop(
+        cancellation_token.clone(),
+        schedule,
+        drifter,
+    ));
+
+    Ok(cancellation_token)
+}
+
+async fn run_local_interval_loop<FDrifter>(
+    cancellation_token: CancellationToken,
+    interval: Duration,
+    drifter: FDrifter,
+) where
+    FDrifter: LocalDrifter,
+{
+    let mut wait = Duration::default();
+    let mut attempt = 0u32;
+    let mut last_success = None;
+    let mut sequence = 0u64;
+
+    loop {
+        let child_token = cancellation_token.child_token();
+        let sleep = time::sleep(wait);
+        tokio::pin!(sleep);
+
+        tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                tracing::trace!("stopping drift job");
+
+                break
+            }
+            _ = &mut sleep => {
+                let start = std::time::Instant::now();
+                let scheduled_at = Utc::now();
+                sequence += 1;
+
+                let ctx = RunContext {
+                    token: child_token,
+                    scheduled_at,
+                    fired_at: scheduled_at,
+                    attempt,
+                    last_success,
+                    sequence,
+                };
+
+                tracing::debug!("running job");
+                if let Err(e) = drifter.execute(ctx).await {
+                    let elapsed = start.elapsed();
+                    wait = interval.saturating_sub(elapsed);
+                    attempt += 1;
+                    tracing::error!("drift job failed with error: {}, waiting: {}s before trying again", e, wait.as_secs());
+                    continue
+                }
+
+                attempt = 0;
+                last_success = Some(Utc::now());
+
+                let elapsed = start.elapsed();
+                wait = interval.saturating_sub(elapsed);
+
+                let now: DateTime<Local> = Local::now();
+                let next: Option<DateTime<Local>> = now.checked_add_signed(TimeDelta::from_std(wait).expect("to be able to convert duration into time delta"));
+
+                tracing::debug!(now=now.to_string(), next=next.map(|n| n.to_string()), "job took: {}ms, waiting: {}ms for next run", elapsed.as_millis(), wait.as_millis() );
+            }
+
+        }
+    }
+}
+
+async fn run_local_cron_loop<FDrifter>(
+    cancellation_token: CancellationToken,
+    schedule: ::cron::Schedule,
+    drifter: FDrifter,
+) where
+    FDrifter: LocalDrifter,
+{
+    let upcoming = schedule.upcoming(Utc {});
+
+    let child_token = cancellation_token.child_token();
+    let mut attempt = 0u32;
+    let mut last_success = None;
+    let mut sequence = 0u64;
+
+    for datetime in upcoming {
+        let now = Utc::now();
+
+        let diff = datetime - now;
+        if diff <= TimeDelta::zero() {
+            tracing::info!(
+                "job schedule for {} was in the past: {}, skipping iteration",
+                datetime.to_string(),
+                now.to_string()
+            );
+            continue;
+        }
+
+        let diff = diff.to_std().expect("to be able to get diff time");
+        let sleep = time::sleep(diff);
+        tokio::pin!(sleep);
+
+        tracing::debug!(
+            "schedule job: {}, waiting: {}s for execution",
+            datetime.to_string(),
+            diff.as_secs()
+        );
+
+        tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                tracing::trace!("stopping drift job");
+
+                break
+            }
+            _ = &mut sleep => {
+                let start = std::time::Instant::now();
+                sequence += 1;
+
+                let ctx = RunContext {
+                    token: child_token.child_token(),
+                    scheduled_at: datetime,
+                    fired_at: Utc::now(),
+                    attempt,
+                    last_success,
+                    sequence,
+                };
+
+                tracing::debug!("running job");
+                if let Err(e) = drifter.execute(ctx).await {
+                    tracing::error!("drift job failed with error: {}", e);
+                    attempt += 1;
+                    continue
+                }
+
+                attempt = 0;
+                last_success = Some(Utc::now());
+
+                let elapsed = start.elapsed();
+
+                tracing::debug!("job took: {}ms ", elapsed.as_millis());
+            }
+
+        }
+    }
+}
+
+struct FuncLocalDrifter<F, Fut>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + 'static,
+{
+    func: Rc<F>,
+}
+
+impl<F, Fut> Clone for FuncLocalDrifter<F, Fut>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            func: self.func.clone(),
+        }
+    }
+}
+
+impl<F, Fut> FuncLocalDrifter<F, Fut>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + 'static,
+{
+    fn new(func: F) -> Self {
+        Self {
+            func: Rc::new(func),
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl<F, Fut> LocalDrifter for FuncLocalDrifter<F, Fut>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + 'static,
+{
+    async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
+        if let Err(e) = (self.func)().await {
+            anyhow::bail!(e)
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_can_schedule_non_send_jobs() {
+        let local_set = LocalSet::new();
+
+        let counter = Rc::new(RefCell::new(0));
+        let token = {
+            let counter = counter.clone();
+            schedule_local(&local_set, Duration::from_millis(50), move || {
+                let counter = counter.clone();
+                async move {
+                    *counter.borrow_mut() += 1;
+                    Ok(())
+                }
+            })
+        };
+
+        local_set
+            .run_until(tokio::time::sleep(Duration::from_millis(150)))
+            .await;
+
+        assert!(!token.is_cancelled());
+        assert!(*counter.borrow() >= 2);
+    }
+}
@@ -0,0 +1,383 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio::{sync::mpsc, time};
+use tokio_util::sync::CancellationToken;
+
+use crate::{Backoff, Drifter, RunContext};
+
+/// Controls what happens when a cron job is still running (or queued) when
+/// its next scheduled tick arrives.
+#[derive(Debug, Clone)]
+pub enum OverlapPolicy {
+    /// Drop ticks that arrive while the previous execution is still
+    /// running. This is the default, and matches running the job
+    /// sequentially against its own cron schedule.
+    Skip,
+    /// Spawn each tick's execution as its own task, so a long-running
+    /// execution doesn't delay the next one.
+    Concurrent,
+    /// Queue ticks that arrive while busy, up to `capacity`, and run them
+    /// back-to-back once the current execution finishes. Ticks beyond
+    /// `capacity` are dropped. A `capacity` of `0` is treated as `1`.
+    Queue { capacity: usize },
+}
+
+/// The consecutive-failure count and last success time, tracked across runs
+/// and shared with overlapping executions so each can build an accurate
+/// [`RunContext`].
+#[derive(Default)]
+struct RunState {
+    attempt: u32,
+    last_success: Option<DateTime<Utc>>,
+}
+
+/// Like [`run_cron_loop_with_retry`], but never retries a failed run (no
+/// [`Backoff`]).
+pub(crate) async fn run_cron_loop<FDrifter>(
+    cancellation_token: CancellationToken,
+    schedule: ::cron::Schedule,
+    drifter: FDrifter,
+    policy: OverlapPolicy,
+) where
+    FDrifter: Drifter + Send + Clone + 'static,
+{
+    run_cron_loop_with_retry(cancellation_token, schedule, drifter, policy, None).await
+}
+
+/// The cron scheduling loop, shared by every `schedule_*_cron*` variant.
+///
+/// `policy` controls what happens when a tick arrives while the previous
+/// execution is still running (see [`OverlapPolicy`]); `retry` optionally
+/// makes a failed execution retry in place, following `Backoff::sleep_duration`,
+/// instead of giving up on that tick until the next one arrives.
+pub(crate) async fn run_cron_loop_with_retry<FDrifter>(
+    cancellation_token: CancellationToken,
+    schedule: ::cron::Schedule,
+    drifter: FDrifter,
+    policy: OverlapPolicy,
+    retry: Option<Backoff>,
+) where
+    FDrifter: Drifter + Send + Clone + 'static,
+{
+    let upcoming = schedule.upcoming(Utc {});
+    let child_token = cancellation_token.child_token();
+    let state = Arc::new(Mutex::new(RunState::default()));
+
+    let queue_tx = match &policy {
+        OverlapPolicy::Queue { capacity } => Some(spawn_queue_worker(
+            child_token.child_token(),
+            drifter.clone(),
+            *capacity,
+            state.clone(),
+            retry,
+        )),
+        _ => None,
+    };
+
+    let mut skipped_ticks: u64 = 0;
+    let mut sequence: u64 = 0;
+
+    for datetime in upcoming {
+        let now = Utc::now();
+
+        let diff = datetime - now;
+        if diff <= TimeDelta::zero() {
+            skipped_ticks += 1;
+            tracing::info!(
+                "job schedule for {} was in the past: {}, skipping iteration (skipped so far: {})",
+                datetime.to_string(),
+                now.to_string(),
+                skipped_ticks
+            );
+            continue;
+        }
+
+        let diff = diff.to_std().expect("to be able to get diff time");
+        let sleep = time::sleep(diff);
+        tokio::pin!(sleep);
+
+        tracing::debug!(
+            "schedule job: {}, waiting: {}s for execution",
+            datetime.to_string(),
+            diff.as_secs()
+        );
+
+        tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                tracing::trace!("stopping drift job");
+
+                break
+            }
+            _ = &mut sleep => {
+                sequence += 1;
+
+                match &policy {
+                    OverlapPolicy::Skip => {
+                        execute_with_retry(&drifter, &child_token, datetime, sequence, &state, retry.as_ref()).await;
+                    }
+                    OverlapPolicy::Concurrent => {
+                        let child_token = child_token.child_token();
+                        let drifter = drifter.clone();
+                        let state = state.clone();
+
+                        tokio::spawn(async move {
+                            execute_with_retry(&drifter, &child_token, datetime, sequence, &state, retry.as_ref()).await;
+                        });
+                    }
+                    OverlapPolicy::Queue { .. } => {
+                        if let Some(tx) = &queue_tx {
+                            if tx.try_send((datetime, sequence)).is_err() {
+                                tracing::warn!(
+                                    "overlap queue full, dropping tick scheduled for {}",
+                                    datetime
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+
+        }
+    }
+}
+
+/// Run `drifter` once, and if it fails and `retry` is set, keep sleeping for
+/// `retry.sleep_duration(attempt)` and retrying until it succeeds or
+/// `token` is cancelled. With no `retry`, a failure is recorded and given up
+/// on immediately, same as before `Backoff` composed with cron scheduling.
+async fn execute_with_retry<FDrifter>(
+    drifter: &FDrifter,
+    token: &CancellationToken,
+    scheduled_at: DateTime<Utc>,
+    sequence: u64,
+    state: &Mutex<RunState>,
+    retry: Option<&Backoff>,
+) where
+    FDrifter: Drifter,
+{
+    loop {
+        let ctx = build_context(token, scheduled_at, sequence, state);
+        let start = std::time::Instant::now();
+
+        tracing::debug!("running job");
+        match drifter.execute(ctx).await {
+            Ok(()) => {
+                record_success(state);
+                let elapsed = start.elapsed();
+                tracing::debug!("job took: {}ms ", elapsed.as_millis());
+
+                return;
+            }
+            Err(e) => {
+                let attempt = {
+                    let mut state = state.lock().unwrap();
+                    let attempt = state.attempt;
+                    state.attempt += 1;
+
+                    attempt
+                };
+
+                match retry {
+                    Some(backoff) => {
+                        let wait = backoff.sleep_duration(attempt);
+                        tracing::error!("drift job failed with error: {}, attempt: {}, retrying in {}s", e, attempt + 1, wait.as_secs());
+
+                        tokio::select! {
+                            _ = token.cancelled() => return,
+                            _ = time::sleep(wait) => continue,
+                        }
+                    }
+                    None => {
+                        tracing::error!("drift job failed with error: {}", e);
+
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn build_context(
+    token: &CancellationToken,
+    scheduled_at: DateTime<Utc>,
+    sequence: u64,
+    state: &Mutex<RunState>,
+) -> RunContext {
+    let state = state.lock().unwrap();
+
+    RunContext {
+        token: token.child_token(),
+        scheduled_at,
+        fired_at: Utc::now(),
+        attempt: state.attempt,
+        last_success: state.last_success,
+        sequence,
+    }
+}
+
+fn record_success(state: &Mutex<RunState>) {
+    let mut state = state.lock().unwrap();
+    state.attempt = 0;
+    state.last_success = Some(Utc::now());
+}
+
+fn spawn_queue_worker<FDrifter>(
+    token: CancellationToken,
+    drifter: FDrifter,
+    capacity: usize,
+    state: Arc<Mutex<RunState>>,
+    retry: Option<Backoff>,
+) -> mpsc::Sender<(DateTime<Utc>, u64)>
+where
+    FDrifter: Drifter + Send + 'static,
+{
+    // `mpsc::channel` panics for a capacity of 0, so clamp instead of
+    // trusting whatever the caller put in `OverlapPolicy::Queue`.
+    let capacity = capacity.max(1);
+    let (tx, mut rx) = mpsc::channel(capacity);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                tick = rx.recv() => {
+                    let Some((scheduled_at, sequence)) = tick else { break };
+
+                    execute_with_retry(&drifter, &token, scheduled_at, sequence, &state, retry.as_ref()).await;
+                }
+            }
+        }
+    });
+
+    tx
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, time::Duration};
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    struct CounterDrifter {
+        counter: Arc<Mutex<usize>>,
+    }
+
+    #[async_trait]
+    impl Drifter for CounterDrifter {
+        async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
+            *self.counter.lock().unwrap() += 1;
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_overlap_runs_ticks_in_parallel() -> anyhow::Result<()> {
+        #[derive(Default, Clone)]
+        struct SlowDrifter {
+            counter: Arc<Mutex<usize>>,
+        }
+
+        #[async_trait]
+        impl Drifter for SlowDrifter {
+            async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
+                *self.counter.lock().unwrap() += 1;
+                tokio::time::sleep(Duration::from_millis(1500)).await;
+
+                Ok(())
+            }
+        }
+
+        let drifter = SlowDrifter::default();
+        let token = CancellationToken::new();
+        let schedule = ::cron::Schedule::from_str("* * * * * *")?;
+
+        tokio::spawn(run_cron_loop(
+            token.clone(),
+            schedule,
+            drifter.clone(),
+            OverlapPolicy::Concurrent,
+        ));
+
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        token.cancel();
+
+        assert!(*drifter.counter.lock().unwrap() >= 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_queue_overlap_runs_ticks_back_to_back() -> anyhow::Result<()> {
+        let drifter = CounterDrifter::default();
+        let token = CancellationToken::new();
+        let schedule = ::cron::Schedule::from_str("* * * * * *")?;
+
+        tokio::spawn(run_cron_loop(
+            token.clone(),
+            schedule,
+            drifter.clone(),
+            OverlapPolicy::Queue { capacity: 0 },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        token.cancel();
+
+        assert!(*drifter.counter.lock().unwrap() >= 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cron_loop_with_retry_retries_failed_tick_before_next_one() -> anyhow::Result<()>
+    {
+        #[derive(Default, Clone)]
+        struct FlakyDrifter {
+            attempts: Arc<Mutex<usize>>,
+        }
+
+        #[async_trait]
+        impl Drifter for FlakyDrifter {
+            async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
+                let mut attempts = self.attempts.lock().unwrap();
+                *attempts += 1;
+
+                if *attempts < 3 {
+                    anyhow::bail!("still flaky");
+                }
+
+                Ok(())
+            }
+        }
+
+        let drifter = FlakyDrifter::default();
+        let token = CancellationToken::new();
+        let schedule = ::cron::Schedule::from_str("* * * * * *")?;
+        let backoff = Backoff::new(Duration::from_millis(10), 1.0, Duration::from_millis(10));
+
+        tokio::spawn(run_cron_loop_with_retry(
+            token.clone(),
+            schedule,
+            drifter.clone(),
+            OverlapPolicy::Skip,
+            Some(backoff),
+        ));
+
+        // The first tick fires within a second; its retries should succeed
+        // well before the next tick a plain `run_cron_loop` would need.
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(1500);
+        while *drifter.attempts.lock().unwrap() < 3 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        token.cancel();
+
+        assert_eq!(*drifter.attempts.lock().unwrap(), 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use tokio::task::JoinError;
+use tokio_util::{sync::CancellationToken, task::JoinMap};
+
+use crate::{run_cron_loop, run_interval_loop, Drifter, OverlapPolicy};
+
+/// A registry of named, independently cancellable drift jobs.
+///
+/// Unlike the free [`schedule`](crate::schedule)/[`schedule_drifter`](crate::schedule_drifter)
+/// functions, which each hand back a lone [`CancellationToken`] for a single
+/// detached task, `Scheduler` owns many jobs keyed by name so a caller can
+/// add, cancel, or replace one schedule without tearing down the others.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: JoinMap<String, ()>,
+    tokens: HashMap<String, CancellationToken>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a job under `name` that runs `drifter` every `interval`.
+    ///
+    /// If a job is already registered under the same name, it is cancelled
+    /// and replaced.
+    pub fn add<FDrifter>(&mut self, name: impl Into<String>, interval: Duration, drifter: FDrifter)
+    where
+        FDrifter: Drifter + Send + 'static + Clone,
+    {
+        let name = name.into();
+        self.cancel(&name);
+
+        let token = CancellationToken::new();
+        self.tokens.insert(name.clone(), token.clone());
+
+        self.jobs
+            .spawn(name, run_interval_loop(token, interval, drifter));
+    }
+
+    /// Register a job under `name` that runs `drifter` on the given cron
+    /// expression.
+    ///
+    /// If a job is already registered under the same name, it is cancelled
+    /// and replaced.
+    pub fn add_cron<FDrifter>(
+        &mut self,
+        name: impl Into<String>,
+        cron: &str,
+        drifter: FDrifter,
+    ) -> anyhow::Result<()>
+    where
+        FDrifter: Drifter + Send + 'static + Clone,
+    {
+        let schedule = ::cron::Schedule::from_str(cron)?;
+        let name = name.into();
+        self.cancel(&name);
+
+        let token = CancellationToken::new();
+        self.tokens.insert(name.clone(), token.clone());
+
+        self.jobs.spawn(
+            name,
+            run_cron_loop(token, schedule, drifter, OverlapPolicy::Skip),
+        );
+
+        Ok(())
+    }
+
+    /// Cancel and forget the job registered under `name`, if any.
+    ///
+    /// Returns `true` if a job was found and cancelled.
+    pub fn cancel(&mut self, name: &str) -> bool {
+        match self.tokens.remove(name) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancel every registered job.
+    pub fn cancel_all(&mut self) {
+        for (_, token) in self.tokens.drain() {
+            token.cancel();
+        }
+    }
+
+    /// The names of jobs currently running.
+    ///
+    /// A cancelled or finished job stays in the underlying `JoinMap` until
+    /// it's drained via [`join_next`](Scheduler::join_next), so this reaps
+    /// anything that has already completed first, and only reports jobs
+    /// that are still executing.
+    pub fn keys(&mut self) -> impl Iterator<Item = &String> {
+        self.reap_finished();
+        self.jobs.keys()
+    }
+
+    /// Non-blocking drain of jobs that have already finished or panicked,
+    /// so [`keys`](Scheduler::keys) doesn't report dead jobs as running.
+    fn reap_finished(&mut self) {
+        while let Some((name, _result)) = self.jobs.try_join_next() {
+            self.tokens.remove(&name);
+        }
+    }
+
+    /// Wait for the next job to complete or panic, returning its name and
+    /// the outcome. Returns `None` once no jobs remain.
+    pub async fn join_next(&mut self) -> Option<(String, Result<(), JoinError>)> {
+        self.jobs.join_next().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use crate::RunContext;
+
+    use super::*;
+
+    #[derive(Default, Clone)]
+    struct CounterDrifter {
+        counter: Arc<Mutex<usize>>,
+    }
+
+    #[async_trait]
+    impl Drifter for CounterDrifter {
+        async fn execute(&self, _ctx: RunContext) -> anyhow::Result<()> {
+            *self.counter.lock().unwrap() += 1;
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_runs_named_jobs_independently() -> anyhow::Result<()> {
+        let drifter_a = CounterDrifter::default();
+        let drifter_b = CounterDrifter::default();
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add("a", Duration::from_millis(50), drifter_a.clone());
+        scheduler.add("b", Duration::from_millis(50), drifter_b.clone());
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+
+        assert_eq!(scheduler.keys().count(), 2);
+
+        assert!(scheduler.cancel("a"));
+        assert!(!scheduler.cancel("a"));
+
+        let count_a = *drifter_a.counter.lock().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(*drifter_a.counter.lock().unwrap(), count_a);
+        assert!(*drifter_b.counter.lock().unwrap() >= 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keys_does_not_report_cancelled_job_as_running() -> anyhow::Result<()> {
+        let drifter = CounterDrifter::default();
+
+        let mut scheduler = Scheduler::new();
+        scheduler.add("a", Duration::from_millis(10), drifter.clone());
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(scheduler.cancel("a"));
+
+        // Give the cancelled task a moment to actually observe the token
+        // and exit its loop, so it's sitting completed in the `JoinMap`.
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        assert_eq!(scheduler.keys().count(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use std::{future::Future, time::Duration};
+
+use chrono::Utc;
+use tokio_util::sync::CancellationToken;
+
+use crate::{DriftError, Drifter, FuncDrifter, RunContext};
+
+/// An adaptive throttling policy that keeps a job from consuming more than
+/// `target_busy_ratio` of wall-clock time, based on a smoothed moving
+/// average of its recent run durations.
+///
+/// After a run taking `avg`, the loop sleeps
+/// `avg * (1.0 / target_busy_ratio - 1.0)`, clamped to `min_delay`/
+/// `max_delay` if set, so a single slow run doesn't cause a huge pause.
+#[derive(Debug, Clone, Copy)]
+pub struct Throttle {
+    pub target_busy_ratio: f64,
+    pub min_delay: Option<Duration>,
+    pub max_delay: Option<Duration>,
+    pub smoothing: f64,
+}
+
+impl Throttle {
+    pub fn new(target_busy_ratio: f64) -> Self {
+        Self {
+            target_busy_ratio,
+            min_delay: None,
+            max_delay: None,
+            smoothing: 0.2,
+        }
+    }
+
+    pub fn with_min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = Some(min_delay);
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = Some(max_delay);
+        self
+    }
+
+    /// The weight given to the latest run when updating the moving average
+    /// of run durations. Defaults to `0.2`.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    fn sleep_for(&self, avg_run: Duration) -> Duration {
+        let ratio = (1.0 / self.target_busy_ratio - 1.0).max(0.0);
+
+        // `ratio` (and therefore `secs`) can be non-finite for a degenerate
+        // `target_busy_ratio` like `0.0`, and `Duration::from_secs_f64`
+        // panics on a non-finite input. Clamp in f64, the same way
+        // `Backoff::delay` clamps before converting to a `Duration`, so a
+        // bad ratio is absorbed by `max_delay` instead of crashing the loop.
+        let mut secs = avg_run.as_secs_f64() * ratio;
+        if !secs.is_finite() {
+            secs = Duration::MAX.as_secs_f64();
+        }
+
+        if let Some(min_delay) = self.min_delay {
+            secs = secs.max(min_delay.as_secs_f64());
+        }
+        if let Some(max_delay) = self.max_delay {
+            secs = secs.min(max_delay.as_secs_f64());
+        }
+
+        Duration::from_secs_f64(secs.min(Duration::MAX.as_secs_f64()).max(0.0))
+    }
+}
+
+pub fn schedule_throttled<F, Fut>(throttle: Throttle, func: F) -> CancellationToken
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), DriftError>> + Send + 'static,
+{
+    let drifter = FuncDrifter::new(func);
+
+    schedule_drifter_throttled(throttle, drifter)
+}
+
+/// Schedule `drifter` to run back-to-back, sleeping between runs so it stays
+/// near `throttle.target_busy_ratio` of wall-clock busy time.
+pub fn schedule_drifter_throttled<FDrifter>(
+    throttle: Throttle,
+    drifter: FDrifter,
+) -> CancellationToken
+where
+    FDrifter: Drifter + Send + 'static + Clone,
+{
+    let cancellation_token = CancellationToken::new();
+
+    tokio::spawn({
+        let cancellation_token = cancellation_token.clone();
+        let drifter = drifter.clone();
+
+        async move {
+            let mut avg_run = Duration::default();
+            let mut wait = Duration::default();
+            let mut attempt = 0u32;
+            let mut last_success = None;
+            let mut sequence = 0u64;
+
+            loop {
+                let child_token = cancellation_token.child_token();
+                let sleep = tokio::time::sleep(wait);
+                tokio::pin!(sleep);
+
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => {
+                        tracing::trace!("stopping drift job");
+
+                        break
+                    }
+                    _ = &mut sleep => {
+                        let start = std::time::Instant::now();
+                        let fired_at = Utc::now();
+                        sequence += 1;
+
+                        let ctx = RunContext {
+                            token: child_token,
+                            scheduled_at: fired_at,
+                            fired_at,
+                            attempt,
+                            last_success,
+                            sequence,
+                        };
+
+                        tracing::debug!("running job");
+                        if let Err(e) = drifter.execute(ctx).await {
+                            tracing::error!("drift job failed with error: {}", e);
+                            attempt += 1;
+                        } else {
+                            attempt = 0;
+                            last_success = Some(Utc::now());
+                        }
+
+                        let elapsed = start.elapsed();
+                        avg_run = if avg_run.is_zero() {
+                            elapsed
+                        } else {
+                            avg_run.mul_f64(1.0 - throttle.smoothing) + elapsed.mul_f64(throttle.smoothing)
+                        };
+
+                        wait = throttle.sleep_for(avg_run);
+
+                        let busy_ratio = avg_run.as_secs_f64() / (avg_run + wait).as_secs_f64().max(f64::EPSILON);
+                        tracing::debug!(
+                            avg_run_ms = avg_run.as_millis() as u64,
+                            wait_ms = wait.as_millis() as u64,
+                            busy_ratio,
+                            "job took: {}ms, throttling to {}ms before next run",
+                            elapsed.as_millis(),
+                            wait.as_millis()
+                        );
+                    }
+                }
+            }
+        }
+    });
+
+    cancellation_token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sleep_for_targets_busy_ratio() {
+        let throttle = Throttle::new(0.5);
+
+        assert_eq!(
+            throttle.sleep_for(Duration::from_millis(100)),
+            Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn test_sleep_for_respects_bounds() {
+        let throttle = Throttle::new(0.1)
+            .with_min_delay(Duration::from_millis(50))
+            .with_max_delay(Duration::from_millis(200));
+
+        // raw delay = 100ms * (1/0.1 - 1) = 900ms, clamped down to max_delay.
+        assert_eq!(
+            throttle.sleep_for(Duration::from_millis(100)),
+            Duration::from_millis(200)
+        );
+        // raw delay = 1ms * 9 = 9ms, clamped up to min_delay.
+        assert_eq!(
+            throttle.sleep_for(Duration::from_millis(1)),
+            Duration::from_millis(50)
+        );
+    }
+
+    #[test]
+    fn test_sleep_for_does_not_panic_on_zero_ratio() {
+        let throttle = Throttle::new(0.0).with_max_delay(Duration::from_millis(200));
+
+        assert_eq!(
+            throttle.sleep_for(Duration::from_millis(100)),
+            Duration::from_millis(200)
+        );
+    }
+}